@@ -1,14 +1,27 @@
 use datafusion::arrow::json::{
-    reader::infer_json_schema_from_iterator, ArrayWriter, ReaderBuilder,
+    reader::{infer_json_schema_from_iterator, Decoder},
+    ArrayWriter, LineDelimitedWriter, ReaderBuilder,
 };
 use serde::de::DeserializeOwned;
 use serde_json::{Map, Value as JsonValue};
-use std::sync::Arc;
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
 
 use crate::{Log, Span};
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use arrow_schema::{DataType, Field, Schema, SchemaRef};
 use datafusion::arrow::array::{Int64Array, RecordBatch, StringArray, UInt64Array};
+use datafusion::arrow::compute::concat_batches;
+use datafusion::arrow::ipc::{reader::StreamReader, writer::StreamWriter};
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use parquet::arrow::arrow_writer::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
 
 pub fn schema_span() -> SchemaRef {
     Arc::new(Schema::new(vec![
@@ -31,10 +44,85 @@ pub fn schema_log() -> SchemaRef {
         Field::new("span_id", DataType::UInt64, true),
         Field::new("level", DataType::Utf8, false),
         Field::new("message", DataType::Utf8, true),
+        Field::new("fields", DataType::Utf8, true),
+    ]))
+}
+
+/// `schema_log` without the opaque `fields` column, used as the merge base for typed fields.
+fn schema_log_base() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new("process_id", DataType::Utf8, false),
+        Field::new("time", DataType::Int64, false),
+        Field::new("trace_id", DataType::UInt64, true),
+        Field::new("span_id", DataType::UInt64, true),
+        Field::new("level", DataType::Utf8, false),
+        Field::new("message", DataType::Utf8, true),
+    ]))
+}
+
+/// The scalar span columns required regardless of `tags`, used to validate a loaded batch.
+fn schema_span_base() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new("id", DataType::UInt64, false),
+        Field::new("parent_id", DataType::UInt64, true),
+        Field::new("trace_id", DataType::UInt64, false),
+        Field::new("name", DataType::Utf8, false),
+        Field::new("process_id", DataType::Utf8, false),
+        Field::new("start", DataType::Int64, false),
+        Field::new("end", DataType::Int64, true),
     ]))
 }
 
 pub fn convert_span_to_record_batch(spans: Vec<Span>) -> Result<RecordBatch> {
+    if spans.is_empty() {
+        return Ok(RecordBatch::new_empty(schema_span()));
+    }
+
+    if spans.iter().all(|span| span.tags.is_empty()) {
+        return convert_span_to_record_batch_untagged(spans);
+    }
+
+    let mut data = Vec::with_capacity(spans.len());
+    let mut tag_objects = Vec::new();
+    for span in spans {
+        let start_time = span.start_as_micros();
+        let end_time = span.end_as_micros();
+        let tags_json = serde_json::to_string(&span.tags)?;
+
+        let mut map = Map::new();
+        map.insert("id".into(), span.id.into());
+        map.insert("parent_id".into(), span.parent_id.into());
+        map.insert("trace_id".into(), span.trace_id.into());
+        map.insert("name".into(), span.name.into());
+        map.insert("process_id".into(), span.process_id.into());
+        map.insert("start".into(), start_time.into());
+        map.insert("end".into(), end_time.into());
+        map.insert("tags".into(), tags_json.into());
+
+        // Namespace tag keys so a tag named like a base column can't collide with it.
+        let mut tag_map = Map::new();
+        for (key, value) in span.tags {
+            tag_map.insert(format!("tag.{key}"), value);
+        }
+        if !tag_map.is_empty() {
+            tag_objects.push(JsonValue::Object(tag_map.clone()));
+            map.extend(tag_map);
+        }
+        data.push(JsonValue::Object(map));
+    }
+
+    // Merge against `schema_span` (already carries `tags`) so the result stays a superset of
+    // the untagged shape and unions cleanly with it.
+    let inferred_tag_schema = infer_json_schema_from_iterator(tag_objects.iter().map(Ok))?;
+    let schema = Schema::try_merge(vec![(*schema_span()).clone(), inferred_tag_schema])?;
+    let mut decoder = ReaderBuilder::new(Arc::new(schema)).build_decoder()?;
+    decoder.serialize(&data)?;
+    let batch = decoder.flush()?.expect("Empty record batch");
+    Ok(batch)
+}
+
+/// Fast path for spans with no tags; also the stable wire shape used by `CollectorClient`.
+fn convert_span_to_record_batch_untagged(spans: Vec<Span>) -> Result<RecordBatch> {
     let mut span_ids = Vec::<u64>::new();
     let mut parent_ids = Vec::<Option<u64>>::new();
     let mut trace_ids = Vec::<u64>::new();
@@ -57,10 +145,6 @@ pub fn convert_span_to_record_batch(spans: Vec<Span>) -> Result<RecordBatch> {
         tags_list.push(serde_json::to_string(&span.tags).unwrap());
     }
 
-    if span_ids.is_empty() {
-        return Ok(RecordBatch::new_empty(schema_span()));
-    }
-
     Ok(RecordBatch::try_new(
         schema_span(),
         vec![
@@ -77,6 +161,10 @@ pub fn convert_span_to_record_batch(spans: Vec<Span>) -> Result<RecordBatch> {
 }
 
 pub fn convert_log_to_record_batch(logs: Vec<Log>) -> Result<RecordBatch> {
+    if logs.iter().all(|log| log.fields.is_empty()) {
+        return convert_log_to_record_batch_untagged(logs);
+    }
+
     let mut data = vec![];
     let mut fields = vec![];
     for log in logs {
@@ -88,9 +176,10 @@ pub fn convert_log_to_record_batch(logs: Vec<Log>) -> Result<RecordBatch> {
         map.insert("level".into(), log.level.as_str().into());
         map.insert("time".into(), time.into());
         map.insert("message".into(), log.message.into());
+        // Namespace field keys, mirroring the `tag.` prefix in convert_span_to_record_batch.
         let mut field_map = Map::new();
         for (key, value) in log.fields {
-            field_map.insert(key.clone(), value.clone());
+            field_map.insert(format!("field.{key}"), value);
         }
 
         if !field_map.is_empty() {
@@ -101,13 +190,48 @@ pub fn convert_log_to_record_batch(logs: Vec<Log>) -> Result<RecordBatch> {
     }
 
     let inferred_field_schema = infer_json_schema_from_iterator(fields.iter().map(Ok))?;
-    let schema = Schema::try_merge(vec![(*schema_log()).clone(), inferred_field_schema]).unwrap();
+    let schema = Schema::try_merge(vec![(*schema_log_base()).clone(), inferred_field_schema])?;
     let mut decoder = ReaderBuilder::new(Arc::new(schema)).build_decoder()?;
     decoder.serialize(&data)?;
     let batch = decoder.flush()?.expect("Empty record batch");
     Ok(batch)
 }
 
+/// Fast path for logs with no fields; also the stable wire shape used by `CollectorClient`.
+fn convert_log_to_record_batch_untagged(logs: Vec<Log>) -> Result<RecordBatch> {
+    let mut process_ids = Vec::<String>::new();
+    let mut times = Vec::<i64>::new();
+    let mut trace_ids = Vec::<Option<u64>>::new();
+    let mut span_ids = Vec::<Option<u64>>::new();
+    let mut levels = Vec::<String>::new();
+    let mut messages = Vec::<Option<String>>::new();
+    let mut fields_list = Vec::<String>::new();
+
+    for log in logs {
+        let time = log.as_micros();
+        process_ids.push(log.process_id);
+        times.push(time);
+        trace_ids.push(log.trace_id);
+        span_ids.push(log.span_id);
+        levels.push(log.level.as_str().to_string());
+        messages.push(log.message);
+        fields_list.push(serde_json::to_string(&log.fields)?);
+    }
+
+    Ok(RecordBatch::try_new(
+        schema_log(),
+        vec![
+            Arc::new(StringArray::from(process_ids)),
+            Arc::new(Int64Array::from(times)),
+            Arc::new(UInt64Array::from(trace_ids)),
+            Arc::new(UInt64Array::from(span_ids)),
+            Arc::new(StringArray::from(levels)),
+            Arc::new(StringArray::from(messages)),
+            Arc::new(StringArray::from(fields_list)),
+        ],
+    )?)
+}
+
 pub fn serialize_record_batches<T: DeserializeOwned>(batch: &[RecordBatch]) -> Result<Vec<T>> {
     if batch.is_empty() {
         return Ok(vec![]);
@@ -121,3 +245,975 @@ pub fn serialize_record_batches<T: DeserializeOwned>(batch: &[RecordBatch]) -> R
     let json_rows: Vec<_> = serde_json::from_reader(json_values.as_slice()).unwrap_or_default();
     Ok(json_rows)
 }
+
+/// Streams `batches` out as newline-delimited JSON without materializing the full result set.
+pub fn export_ndjson<W: Write>(batches: impl IntoIterator<Item = RecordBatch>, w: W) -> Result<()> {
+    let mut writer = LineDelimitedWriter::new(w);
+    for batch in batches {
+        if batch.num_rows() == 0 {
+            continue;
+        }
+        writer.write(&batch)?;
+    }
+    writer.finish()?;
+    Ok(())
+}
+
+/// Like [`export_ndjson`] but yields each row as its own `String`.
+pub fn ndjson_rows(batches: impl IntoIterator<Item = RecordBatch>) -> impl Iterator<Item = Result<String>> {
+    batches.into_iter().flat_map(|batch| -> Vec<Result<String>> {
+        if batch.num_rows() == 0 {
+            return vec![];
+        }
+        let rows = (|| -> Result<Vec<String>> {
+            let mut writer = LineDelimitedWriter::new(Vec::new());
+            writer.write(&batch)?;
+            writer.finish()?;
+            let bytes = writer.into_inner();
+            Ok(String::from_utf8(bytes)?.lines().map(str::to_string).collect())
+        })();
+        match rows {
+            Ok(rows) => rows.into_iter().map(Ok).collect(),
+            Err(e) => vec![Err(e)],
+        }
+    })
+}
+
+/// Sink for record batches that are durably persisted somewhere other than memory.
+pub trait RecordBatchWriter {
+    fn write(&mut self, batch: &RecordBatch) -> Result<()>;
+    fn close(self) -> Result<()>;
+}
+
+const HOUR_MICROS: i64 = 60 * 60 * 1_000_000;
+
+/// Writes record batches to Parquet files partitioned by hour, keyed on a timestamp column
+/// (`start` for spans, `time` for logs). Files are named `{hour}-{seq}.parquet`.
+pub struct ParquetPartitionWriter {
+    dir: PathBuf,
+    time_column: String,
+    writers: BTreeMap<i64, (SchemaRef, ArrowWriter<File>)>,
+}
+
+impl ParquetPartitionWriter {
+    pub fn new(dir: impl Into<PathBuf>, time_column: &str) -> Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        Ok(Self {
+            dir,
+            time_column: time_column.to_string(),
+            writers: BTreeMap::new(),
+        })
+    }
+
+    fn writer_properties() -> WriterProperties {
+        WriterProperties::builder()
+            .set_compression(Compression::ZSTD(Default::default()))
+            .build()
+    }
+
+    fn writer_for_hour(&mut self, hour: i64, schema: &SchemaRef) -> Result<&mut ArrowWriter<File>> {
+        if let Some((existing_schema, _)) = self.writers.get(&hour) {
+            if existing_schema != schema {
+                return Err(anyhow!(
+                    "cannot write a batch with schema {schema:?} to the `{hour}` \
+                     partition: it's already open with schema {existing_schema:?}"
+                ));
+            }
+        } else {
+            let path = self.next_file_for_hour(hour)?;
+            let file = File::create(&path)?;
+            let writer = ArrowWriter::try_new(file, schema.clone(), Some(Self::writer_properties()))?;
+            self.writers.insert(hour, (schema.clone(), writer));
+        }
+        Ok(&mut self.writers.get_mut(&hour).unwrap().1)
+    }
+
+    /// Picks a filename for `hour` that doesn't already exist, so a re-flush doesn't truncate
+    /// a file left over from an earlier one.
+    fn next_file_for_hour(&self, hour: i64) -> Result<PathBuf> {
+        for seq in 0.. {
+            let path = self.dir.join(format!("{hour}-{seq}.parquet"));
+            if !path.exists() {
+                return Ok(path);
+            }
+        }
+        unreachable!()
+    }
+}
+
+impl RecordBatchWriter for ParquetPartitionWriter {
+    fn write(&mut self, batch: &RecordBatch) -> Result<()> {
+        let schema = batch.schema();
+        let time_idx = schema.index_of(&self.time_column)?;
+        let time_col = batch
+            .column(time_idx)
+            .as_any()
+            .downcast_ref::<Int64Array>()
+            .ok_or_else(|| anyhow!("time column `{}` is not Int64", self.time_column))?;
+
+        let mut hours: BTreeMap<i64, Vec<usize>> = BTreeMap::new();
+        for (row, value) in time_col.iter().enumerate() {
+            let value = value.ok_or_else(|| anyhow!("time column `{}` cannot be null", self.time_column))?;
+            hours.entry(value.div_euclid(HOUR_MICROS)).or_default().push(row);
+        }
+
+        for (hour, rows) in hours {
+            let indices = datafusion::arrow::array::UInt32Array::from(
+                rows.into_iter().map(|r| r as u32).collect::<Vec<_>>(),
+            );
+            let columns = batch
+                .columns()
+                .iter()
+                .map(|col| datafusion::arrow::compute::take(col, &indices, None))
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+            let partitioned = RecordBatch::try_new(schema.clone(), columns)?;
+            self.writer_for_hour(hour, &schema)?.write(&partitioned)?;
+        }
+        Ok(())
+    }
+
+    fn close(self) -> Result<()> {
+        for (_, (_, writer)) in self.writers {
+            writer.close()?;
+        }
+        Ok(())
+    }
+}
+
+/// An append-only span store that transparently unions spans still held in memory with
+/// spans already flushed to Parquet, so the query layer doesn't need to know which is which.
+pub struct SpanStore {
+    dir: PathBuf,
+    in_memory: Vec<RecordBatch>,
+}
+
+impl SpanStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            in_memory: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, batch: RecordBatch) {
+        if batch.num_rows() > 0 {
+            self.in_memory.push(batch);
+        }
+    }
+
+    /// Writes all in-memory span batches to Parquet under `self.dir` and clears them from
+    /// memory. Batches are widened to their common union schema first, since different
+    /// pushes can carry different `tag.*` columns and a single Parquet file can't mix schemas.
+    pub fn flush_to_parquet(&mut self) -> Result<()> {
+        if self.in_memory.is_empty() {
+            return Ok(());
+        }
+
+        let union_schema = self.in_memory.iter().try_fold(schema_span(), |acc, batch| {
+            Result::<SchemaRef>::Ok(Arc::new(Schema::try_merge(vec![
+                (*acc).clone(),
+                (*batch.schema()).clone(),
+            ])?))
+        })?;
+
+        let mut writer = ParquetPartitionWriter::new(&self.dir, "start")?;
+        for batch in &self.in_memory {
+            writer.write(&Self::align_to_schema(batch, &union_schema)?)?;
+        }
+        writer.close()?;
+        self.in_memory.clear();
+        Ok(())
+    }
+
+    /// Widens `batch` to `schema`, filling any column it's missing with an all-null array.
+    fn align_to_schema(batch: &RecordBatch, schema: &SchemaRef) -> Result<RecordBatch> {
+        let columns = schema
+            .fields()
+            .iter()
+            .map(|field| match batch.column_by_name(field.name()) {
+                Some(column) => column.clone(),
+                None => datafusion::arrow::array::new_null_array(field.data_type(), batch.num_rows()),
+            })
+            .collect();
+        Ok(RecordBatch::try_new(schema.clone(), columns)?)
+    }
+
+    /// Opens a store backed by `path`. Hydration is lazy: [`SpanStore::batches`] reads
+    /// previously flushed Parquet files from disk on demand rather than up front.
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        Ok(Self {
+            dir: path.into(),
+            in_memory: Vec::new(),
+        })
+    }
+
+    /// Returns on-disk and in-memory batches combined, pruning Parquet files whose
+    /// `trace_id`/`start` statistics can't satisfy the given filters.
+    pub fn batches(
+        &self,
+        trace_id_filter: Option<u64>,
+        start_range: Option<(i64, i64)>,
+    ) -> Result<Vec<RecordBatch>> {
+        let mut batches = self.read_parquet_files(trace_id_filter, start_range)?;
+        batches.extend(self.in_memory.iter().cloned());
+        Ok(batches)
+    }
+
+    fn read_parquet_files(
+        &self,
+        trace_id_filter: Option<u64>,
+        start_range: Option<(i64, i64)>,
+    ) -> Result<Vec<RecordBatch>> {
+        if !self.dir.exists() {
+            return Ok(vec![]);
+        }
+
+        let mut paths: Vec<_> = fs::read_dir(&self.dir)?
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|path| path.extension().map(|ext| ext == "parquet").unwrap_or(false))
+            .collect();
+        paths.sort();
+
+        let mut out = Vec::new();
+        for path in paths {
+            // The hour bucket in the filename can rule out a file before even opening it.
+            if let Some((lo_bound, hi_bound)) = start_range {
+                if let Some(hour) = Self::hour_from_path(&path) {
+                    let file_start = hour * HOUR_MICROS;
+                    let file_end = file_start + HOUR_MICROS - 1;
+                    if file_end < lo_bound || file_start > hi_bound {
+                        continue;
+                    }
+                }
+            }
+
+            let file = File::open(&path)?;
+            let builder = ParquetRecordBatchReaderBuilder::try_new(file)?;
+            let row_groups = Self::matching_row_groups(&builder, trace_id_filter, start_range);
+            if row_groups.is_empty() {
+                continue;
+            }
+
+            let reader = builder.with_row_groups(row_groups).build()?;
+            let batches: Vec<RecordBatch> = reader.collect::<std::result::Result<_, _>>()?;
+            if batches.is_empty() {
+                continue;
+            }
+            out.push(concat_batches(&batches[0].schema(), &batches)?);
+        }
+        Ok(out)
+    }
+
+    fn hour_from_path(path: &Path) -> Option<i64> {
+        let stem = path.file_stem()?.to_str()?;
+        stem.split('-').next()?.parse().ok()
+    }
+
+    /// Selects the row groups whose footer statistics can't rule out a match, so callers
+    /// only decode row groups that can possibly contain matching rows.
+    fn matching_row_groups(
+        builder: &ParquetRecordBatchReaderBuilder<File>,
+        trace_id_filter: Option<u64>,
+        start_range: Option<(i64, i64)>,
+    ) -> Vec<usize> {
+        let metadata = builder.metadata();
+        let schema = builder.schema();
+        let trace_id_idx = schema.index_of("trace_id").ok();
+        let start_idx = schema.index_of("start").ok();
+
+        (0..metadata.num_row_groups())
+            .filter(|&i| {
+                let row_group = metadata.row_group(i);
+                if let (Some(trace_id), Some(idx)) = (trace_id_filter, trace_id_idx) {
+                    if let Some((lo, hi)) = Self::int64_column_stats(row_group, idx) {
+                        if trace_id < lo as u64 || trace_id > hi as u64 {
+                            return false;
+                        }
+                    }
+                }
+                if let (Some((lo_bound, hi_bound)), Some(idx)) = (start_range, start_idx) {
+                    if let Some((lo, hi)) = Self::int64_column_stats(row_group, idx) {
+                        if hi < lo_bound || lo > hi_bound {
+                            return false;
+                        }
+                    }
+                }
+                true
+            })
+            .collect()
+    }
+
+    /// Reads min/max statistics for an `Int64`-physical-type column (covers both `start` and
+    /// `trace_id`, since Arrow `UInt64` is stored as `Int64` physically). `None` if absent.
+    fn int64_column_stats(
+        row_group: &parquet::file::metadata::RowGroupMetaData,
+        column_idx: usize,
+    ) -> Option<(i64, i64)> {
+        match row_group.column(column_idx).statistics()? {
+            parquet::file::statistics::Statistics::Int64(stats) => {
+                Some((*stats.min_opt()?, *stats.max_opt()?))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Push-based ingestion over a fixed [`Decoder`] schema: feed raw JSON bytes in arbitrary
+/// chunks and get back whole batches once the configured row threshold is reached.
+struct Ingestor {
+    decoder: Decoder,
+    pending: Vec<u8>,
+}
+
+impl Ingestor {
+    fn new(schema: SchemaRef, row_threshold: usize) -> Result<Self> {
+        let decoder = ReaderBuilder::new(schema)
+            .with_batch_size(row_threshold)
+            .build_decoder()?;
+        Ok(Self {
+            decoder,
+            pending: Vec::new(),
+        })
+    }
+
+    /// Feeds `chunk` to the decoder, returning any batches that filled up to the row
+    /// threshold. A still-incomplete trailing object is kept by the decoder itself and
+    /// resumed on the next call; `pending` only holds bytes left unread when the decoder
+    /// stopped at a full batch.
+    fn feed(&mut self, chunk: &[u8]) -> Result<Vec<RecordBatch>> {
+        self.pending.extend_from_slice(chunk);
+        let mut flushed = Vec::new();
+        loop {
+            let consumed = self.decoder.decode(&self.pending)?;
+            self.pending.drain(..consumed);
+
+            if self.decoder.capacity() > 0 || self.decoder.has_partial_record() {
+                // Not full yet, or the last row is still incomplete: wait for more bytes.
+                break;
+            }
+
+            match self.decoder.flush()? {
+                Some(batch) => flushed.push(batch),
+                None => break,
+            }
+        }
+        Ok(flushed)
+    }
+
+    /// Flushes whatever rows are buffered, even below the row threshold.
+    fn flush(&mut self) -> Result<Option<RecordBatch>> {
+        Ok(self.decoder.flush()?)
+    }
+}
+
+/// Incrementally ingests raw span JSON into `RecordBatch`es as rows accumulate.
+pub struct SpanIngestor(Ingestor);
+
+impl SpanIngestor {
+    pub fn new(row_threshold: usize) -> Result<Self> {
+        Ok(Self(Ingestor::new(schema_span(), row_threshold)?))
+    }
+
+    pub fn feed(&mut self, chunk: &[u8]) -> Result<Vec<RecordBatch>> {
+        self.0.feed(chunk)
+    }
+
+    pub fn flush(&mut self) -> Result<Option<RecordBatch>> {
+        self.0.flush()
+    }
+}
+
+/// Incrementally ingests raw log JSON the same way [`SpanIngestor`] does for spans, against
+/// the static [`schema_log`] rather than a per-call schema inferred from buffered `fields`.
+pub struct LogIngestor(Ingestor);
+
+impl LogIngestor {
+    pub fn new(row_threshold: usize) -> Result<Self> {
+        Ok(Self(Ingestor::new(schema_log(), row_threshold)?))
+    }
+
+    pub fn feed(&mut self, chunk: &[u8]) -> Result<Vec<RecordBatch>> {
+        self.0.feed(chunk)
+    }
+
+    pub fn flush(&mut self) -> Result<Option<RecordBatch>> {
+        self.0.flush()
+    }
+}
+
+/// Writes `batch` as a single length-prefixed Arrow IPC stream section.
+fn write_ipc_section<W: Write>(w: &mut W, batch: &RecordBatch) -> Result<()> {
+    let mut buf = Vec::new();
+    let mut writer = StreamWriter::try_new(&mut buf, &batch.schema())?;
+    writer.write(batch)?;
+    writer.finish()?;
+    drop(writer);
+
+    w.write_all(&(buf.len() as u64).to_le_bytes())?;
+    w.write_all(&buf)?;
+    Ok(())
+}
+
+fn read_ipc_section<R: Read>(r: &mut R) -> Result<RecordBatch> {
+    let mut len_bytes = [0u8; 8];
+    r.read_exact(&mut len_bytes)?;
+    let len = u64::from_le_bytes(len_bytes) as usize;
+
+    let mut buf = vec![0u8; len];
+    r.read_exact(&mut buf)?;
+
+    let mut reader = StreamReader::try_new(buf.as_slice(), None)?;
+    reader
+        .next()
+        .ok_or_else(|| anyhow!("IPC section contained no record batch"))?
+        .map_err(Into::into)
+}
+
+/// Checkpoints spans and logs as two length-prefixed Arrow IPC sections, so reloading
+/// doesn't require re-parsing JSON.
+pub fn snapshot_to_ipc<W: Write>(spans: &RecordBatch, logs: &RecordBatch, mut w: W) -> Result<()> {
+    write_ipc_section(&mut w, spans)?;
+    write_ipc_section(&mut w, logs)?;
+    Ok(())
+}
+
+/// Reconstructs the span and log batches written by [`snapshot_to_ipc`]. The span batch is
+/// checked against [`schema_span_base`] so an incompatible snapshot fails loudly.
+pub fn load_from_ipc<R: Read>(mut r: R) -> Result<(RecordBatch, RecordBatch)> {
+    let spans = read_ipc_section(&mut r)?;
+    let logs = read_ipc_section(&mut r)?;
+
+    for field in schema_span_base().fields() {
+        match spans.schema().field_with_name(field.name()) {
+            Ok(found) if found.data_type() == field.data_type() => {}
+            _ => {
+                return Err(anyhow!(
+                    "snapshot span schema is incompatible with the current span schema: \
+                     missing or mismatched field `{}`",
+                    field.name()
+                ))
+            }
+        }
+    }
+
+    Ok((spans, logs))
+}
+
+const FRAME_HANDSHAKE: u8 = 0;
+const FRAME_SPAN: u8 = 1;
+const FRAME_LOG: u8 = 2;
+
+/// Writes a length-prefixed frame: a one-byte kind tag followed by an 8-byte payload length.
+fn write_frame<W: Write>(w: &mut W, kind: u8, payload: &[u8]) -> Result<()> {
+    w.write_all(&[kind])?;
+    w.write_all(&(payload.len() as u64).to_le_bytes())?;
+    w.write_all(payload)?;
+    Ok(())
+}
+
+/// Reads one frame written by [`write_frame`], or `None` on a clean disconnect.
+fn read_frame<R: Read>(r: &mut R) -> Result<Option<(u8, Vec<u8>)>> {
+    let mut kind = [0u8; 1];
+    if r.read(&mut kind)? == 0 {
+        return Ok(None);
+    }
+
+    let mut len_bytes = [0u8; 8];
+    r.read_exact(&mut len_bytes)?;
+    let len = u64::from_le_bytes(len_bytes) as usize;
+
+    let mut payload = vec![0u8; len];
+    r.read_exact(&mut payload)?;
+    Ok(Some((kind[0], payload)))
+}
+
+/// Overwrites every row of `batch`'s `process_id` column, in case a worker mislabeled it.
+fn tag_process_id(batch: &RecordBatch, process_id: &str) -> Result<RecordBatch> {
+    let idx = batch.schema().index_of("process_id")?;
+    let mut columns = batch.columns().to_vec();
+    columns[idx] = Arc::new(StringArray::from(vec![process_id; batch.num_rows()]));
+    Ok(RecordBatch::try_new(batch.schema(), columns)?)
+}
+
+/// One worker's side of remote collection: decodes NDJSON span/log frames and tags the
+/// resulting batches with the worker's `process_id`.
+struct CollectorConnection {
+    process_id: String,
+    span_ingestor: SpanIngestor,
+    log_ingestor: LogIngestor,
+}
+
+impl CollectorConnection {
+    fn new(process_id: impl Into<String>, row_threshold: usize) -> Result<Self> {
+        Ok(Self {
+            process_id: process_id.into(),
+            span_ingestor: SpanIngestor::new(row_threshold)?,
+            log_ingestor: LogIngestor::new(row_threshold)?,
+        })
+    }
+
+    fn feed_spans(&mut self, chunk: &[u8]) -> Result<Vec<RecordBatch>> {
+        self.span_ingestor
+            .feed(chunk)?
+            .iter()
+            .map(|batch| tag_process_id(batch, &self.process_id))
+            .collect()
+    }
+
+    fn feed_logs(&mut self, chunk: &[u8]) -> Result<Vec<RecordBatch>> {
+        self.log_ingestor
+            .feed(chunk)?
+            .iter()
+            .map(|batch| tag_process_id(batch, &self.process_id))
+            .collect()
+    }
+
+    /// Flushes each ingestor's trailing partial batch on disconnect.
+    fn finish(&mut self) -> Result<(Option<RecordBatch>, Option<RecordBatch>)> {
+        let spans = self
+            .span_ingestor
+            .flush()?
+            .map(|b| tag_process_id(&b, &self.process_id))
+            .transpose()?;
+        let logs = self
+            .log_ingestor
+            .flush()?
+            .map(|b| tag_process_id(&b, &self.process_id))
+            .transpose()?;
+        Ok((spans, logs))
+    }
+}
+
+/// The central store's merged view: spans/logs gathered from any number of worker connections.
+#[derive(Default)]
+pub struct CollectedBatches {
+    spans: Vec<RecordBatch>,
+    logs: Vec<RecordBatch>,
+}
+
+impl CollectedBatches {
+    pub fn merge_spans(&mut self, batch: RecordBatch) {
+        self.spans.push(batch);
+    }
+
+    pub fn merge_logs(&mut self, batch: RecordBatch) {
+        self.logs.push(batch);
+    }
+
+    pub fn spans(&self) -> &[RecordBatch] {
+        &self.spans
+    }
+
+    pub fn logs(&self) -> &[RecordBatch] {
+        &self.logs
+    }
+}
+
+/// Accepts spans/logs emitted by separate worker processes and merges them into one central
+/// `CollectedBatches`.
+pub struct Collector {
+    listener: TcpListener,
+}
+
+impl Collector {
+    pub fn bind(addr: impl ToSocketAddrs) -> Result<Self> {
+        Ok(Self {
+            listener: TcpListener::bind(addr)?,
+        })
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(self.listener.local_addr()?)
+    }
+
+    /// Accepts worker connections forever, handling each on its own thread.
+    pub fn serve(self, store: Arc<Mutex<CollectedBatches>>) -> Result<()> {
+        for stream in self.listener.incoming() {
+            let stream = stream?;
+            let store = store.clone();
+            thread::spawn(move || {
+                if let Err(err) = Self::handle_connection(stream, &store) {
+                    eprintln!("collector: worker connection ended: {err}");
+                }
+            });
+        }
+        Ok(())
+    }
+
+    fn handle_connection(mut stream: TcpStream, store: &Mutex<CollectedBatches>) -> Result<()> {
+        let (kind, payload) = read_frame(&mut stream)?
+            .ok_or_else(|| anyhow!("worker disconnected before handshaking"))?;
+        if kind != FRAME_HANDSHAKE {
+            return Err(anyhow!("expected a handshake frame, got kind {kind}"));
+        }
+        let mut conn = CollectorConnection::new(String::from_utf8(payload)?, 1024)?;
+
+        while let Some((kind, payload)) = read_frame(&mut stream)? {
+            let batches = match kind {
+                FRAME_SPAN => conn.feed_spans(&payload)?,
+                FRAME_LOG => conn.feed_logs(&payload)?,
+                other => return Err(anyhow!("unknown collector frame kind `{other}`")),
+            };
+            let mut store = store.lock().unwrap();
+            for batch in batches {
+                match kind {
+                    FRAME_SPAN => store.merge_spans(batch),
+                    _ => store.merge_logs(batch),
+                }
+            }
+        }
+
+        // The worker disconnected; don't drop whatever it had buffered below the threshold.
+        let (spans, logs) = conn.finish()?;
+        let mut store = store.lock().unwrap();
+        if let Some(batch) = spans {
+            store.merge_spans(batch);
+        }
+        if let Some(batch) = logs {
+            store.merge_logs(batch);
+        }
+        Ok(())
+    }
+}
+
+/// Client side of remote collection: buffers `Span`/`Log` values and ships them to a
+/// [`Collector`] as NDJSON once `batch_size` rows have accumulated.
+pub struct CollectorClient {
+    stream: TcpStream,
+    batch_size: usize,
+    pending_spans: Vec<Span>,
+    pending_logs: Vec<Log>,
+}
+
+impl CollectorClient {
+    pub fn connect(addr: impl ToSocketAddrs, process_id: &str, batch_size: usize) -> Result<Self> {
+        let mut stream = TcpStream::connect(addr)?;
+        write_frame(&mut stream, FRAME_HANDSHAKE, process_id.as_bytes())?;
+        Ok(Self {
+            stream,
+            batch_size,
+            pending_spans: Vec::new(),
+            pending_logs: Vec::new(),
+        })
+    }
+
+    pub fn push_span(&mut self, span: Span) -> Result<()> {
+        self.pending_spans.push(span);
+        if self.pending_spans.len() >= self.batch_size {
+            self.flush_spans()?;
+        }
+        Ok(())
+    }
+
+    pub fn push_log(&mut self, log: Log) -> Result<()> {
+        self.pending_logs.push(log);
+        if self.pending_logs.len() >= self.batch_size {
+            self.flush_logs()?;
+        }
+        Ok(())
+    }
+
+    /// Ships whatever spans are buffered, regardless of `batch_size` — e.g. on shutdown.
+    /// Always uses the `tags`-as-JSON-string shape: the collector decodes against the
+    /// static [`schema_span`], so typed tag columns would silently drop on arrival.
+    pub fn flush_spans(&mut self) -> Result<()> {
+        if self.pending_spans.is_empty() {
+            return Ok(());
+        }
+        let batch = convert_span_to_record_batch_untagged(std::mem::take(&mut self.pending_spans))?;
+        let mut payload = Vec::new();
+        export_ndjson([batch], &mut payload)?;
+        write_frame(&mut self.stream, FRAME_SPAN, &payload)
+    }
+
+    /// Ships whatever logs are buffered, regardless of `batch_size` — e.g. on shutdown.
+    /// Like `flush_spans`, always uses the `fields`-as-JSON-string shape for the same reason.
+    pub fn flush_logs(&mut self) -> Result<()> {
+        if self.pending_logs.is_empty() {
+            return Ok(());
+        }
+        let batch = convert_log_to_record_batch_untagged(std::mem::take(&mut self.pending_logs))?;
+        let mut payload = Vec::new();
+        export_ndjson([batch], &mut payload)?;
+        write_frame(&mut self.stream, FRAME_LOG, &payload)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("duet-arrow-test-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn sample_span_batch(id: u64, process_id: &str, start: i64) -> RecordBatch {
+        RecordBatch::try_new(
+            schema_span(),
+            vec![
+                Arc::new(UInt64Array::from(vec![id])),
+                Arc::new(UInt64Array::from(vec![None::<u64>])),
+                Arc::new(UInt64Array::from(vec![id])),
+                Arc::new(StringArray::from(vec!["span"])),
+                Arc::new(StringArray::from(vec![process_id])),
+                Arc::new(Int64Array::from(vec![start])),
+                Arc::new(Int64Array::from(vec![None::<i64>])),
+                Arc::new(StringArray::from(vec!["{}"])),
+            ],
+        )
+        .unwrap()
+    }
+
+    fn span_json_line(id: u64, process_id: &str, start: i64) -> String {
+        json!({
+            "id": id,
+            "parent_id": null,
+            "trace_id": id,
+            "name": "span",
+            "process_id": process_id,
+            "start": start,
+            "end": null,
+            "tags": "{}",
+        })
+        .to_string()
+    }
+
+    fn log_json_line(process_id: &str, time: i64, fields: &str) -> String {
+        json!({
+            "process_id": process_id,
+            "time": time,
+            "trace_id": null,
+            "span_id": null,
+            "level": "info",
+            "message": "hello",
+            "fields": fields,
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn export_ndjson_emits_nothing_for_empty_batches() {
+        let empty = RecordBatch::new_empty(schema_span());
+        let mut buf = Vec::new();
+        export_ndjson([empty], &mut buf).unwrap();
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn ingestor_flushes_only_at_threshold() {
+        let mut ingestor = SpanIngestor::new(2).unwrap();
+
+        let first = ingestor.feed(span_json_line(1, "p1", 100).as_bytes()).unwrap();
+        assert!(first.is_empty(), "a single row should stay buffered below the threshold");
+
+        let second = ingestor.feed(span_json_line(2, "p1", 200).as_bytes()).unwrap();
+        assert_eq!(second.len(), 1);
+        assert_eq!(second[0].num_rows(), 2);
+    }
+
+    #[test]
+    fn ingestor_retains_partial_bytes_across_feeds() {
+        let mut ingestor = SpanIngestor::new(1).unwrap();
+        let line = span_json_line(1, "p1", 100);
+        let (head, tail) = line.split_at(line.len() / 2);
+
+        let first = ingestor.feed(head.as_bytes()).unwrap();
+        assert!(first.is_empty());
+
+        let second = ingestor.feed(tail.as_bytes()).unwrap();
+        assert_eq!(second.len(), 1);
+        assert_eq!(second[0].num_rows(), 1);
+    }
+
+    #[test]
+    fn ipc_snapshot_round_trips_spans_and_logs() {
+        let spans = sample_span_batch(1, "p1", 0);
+        let logs = RecordBatch::try_new(
+            schema_log(),
+            vec![
+                Arc::new(StringArray::from(vec!["p1"])),
+                Arc::new(Int64Array::from(vec![0i64])),
+                Arc::new(UInt64Array::from(vec![Some(1u64)])),
+                Arc::new(UInt64Array::from(vec![Some(1u64)])),
+                Arc::new(StringArray::from(vec!["info"])),
+                Arc::new(StringArray::from(vec![Some("hello")])),
+                Arc::new(StringArray::from(vec![Some("{}")])),
+            ],
+        )
+        .unwrap();
+
+        let mut buf = Vec::new();
+        snapshot_to_ipc(&spans, &logs, &mut buf).unwrap();
+
+        let (loaded_spans, loaded_logs) = load_from_ipc(buf.as_slice()).unwrap();
+        assert_eq!(loaded_spans.num_rows(), 1);
+        assert_eq!(loaded_logs.num_rows(), 1);
+    }
+
+    #[test]
+    fn load_from_ipc_rejects_incompatible_span_schema() {
+        let bogus_schema: SchemaRef = Arc::new(Schema::new(vec![Field::new("only_field", DataType::Utf8, false)]));
+        let bogus = RecordBatch::try_new(bogus_schema, vec![Arc::new(StringArray::from(vec!["x"]))]).unwrap();
+        let logs = RecordBatch::new_empty(schema_log());
+
+        let mut buf = Vec::new();
+        snapshot_to_ipc(&bogus, &logs, &mut buf).unwrap();
+
+        let err = load_from_ipc(buf.as_slice()).unwrap_err();
+        assert!(err.to_string().contains("incompatible"));
+    }
+
+    #[test]
+    fn span_store_round_trips_through_parquet_with_pruning() {
+        let dir = temp_dir("span-store-round-trip");
+
+        let mut store = SpanStore::new(&dir);
+        store.push(sample_span_batch(1, "p1", 0));
+        store.push(sample_span_batch(2, "p1", 2 * HOUR_MICROS));
+        store.flush_to_parquet().unwrap();
+
+        let reopened = SpanStore::open(&dir).unwrap();
+        let all = reopened.batches(None, None).unwrap();
+        assert_eq!(all.iter().map(|b| b.num_rows()).sum::<usize>(), 2);
+
+        let pruned = reopened
+            .batches(None, Some((HOUR_MICROS, 2 * HOUR_MICROS - 1)))
+            .unwrap();
+        assert_eq!(pruned.iter().map(|b| b.num_rows()).sum::<usize>(), 0);
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn span_store_keeps_both_flushes_into_the_same_hour() {
+        let dir = temp_dir("span-store-double-flush");
+
+        let mut store = SpanStore::new(&dir);
+        store.push(sample_span_batch(1, "p1", 0));
+        store.flush_to_parquet().unwrap();
+
+        let mut store = SpanStore::new(&dir);
+        store.push(sample_span_batch(2, "p1", 1));
+        store.flush_to_parquet().unwrap();
+
+        let reopened = SpanStore::open(&dir).unwrap();
+        let all = reopened.batches(None, None).unwrap();
+        assert_eq!(all.iter().map(|b| b.num_rows()).sum::<usize>(), 2);
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn span_store_unions_tagged_and_untagged_batches_in_one_flush() {
+        let dir = temp_dir("span-store-schema-union");
+
+        let tagged_schema: SchemaRef = Arc::new(
+            Schema::try_merge(vec![
+                (*schema_span()).clone(),
+                Schema::new(vec![Field::new("tag.user", DataType::Utf8, true)]),
+            ])
+            .unwrap(),
+        );
+        let untagged = sample_span_batch(1, "p1", 0);
+        let tagged_columns = untagged
+            .columns()
+            .iter()
+            .cloned()
+            .chain([Arc::new(StringArray::from(vec![Some("alice")])) as _])
+            .collect();
+        let tagged = RecordBatch::try_new(tagged_schema, tagged_columns).unwrap();
+
+        let mut store = SpanStore::new(&dir);
+        store.push(untagged);
+        store.push(tagged);
+        store.flush_to_parquet().unwrap();
+
+        let reopened = SpanStore::open(&dir).unwrap();
+        let all = reopened.batches(None, None).unwrap();
+        assert_eq!(all.iter().map(|b| b.num_rows()).sum::<usize>(), 2);
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn parquet_partition_writer_rejects_mismatched_schema_in_same_hour() {
+        let dir = temp_dir("schema-mismatch");
+        let mut writer = ParquetPartitionWriter::new(&dir, "start").unwrap();
+        writer.write(&sample_span_batch(1, "p1", 0)).unwrap();
+
+        let mismatched = RecordBatch::try_new(
+            schema_span_base(),
+            vec![
+                Arc::new(UInt64Array::from(vec![2u64])),
+                Arc::new(UInt64Array::from(vec![None::<u64>])),
+                Arc::new(UInt64Array::from(vec![2u64])),
+                Arc::new(StringArray::from(vec!["span"])),
+                Arc::new(StringArray::from(vec!["p1"])),
+                Arc::new(Int64Array::from(vec![1i64])),
+                Arc::new(Int64Array::from(vec![None::<i64>])),
+            ],
+        )
+        .unwrap();
+
+        let err = writer.write(&mismatched).unwrap_err();
+        assert!(err.to_string().contains("schema"));
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn collector_connection_tags_process_id_on_ingest() {
+        let mut conn = CollectorConnection::new("worker-central", 1).unwrap();
+        let line = span_json_line(1, "worker-that-lied", 100);
+
+        let batches = conn.feed_spans(line.as_bytes()).unwrap();
+        assert_eq!(batches.len(), 1);
+
+        let process_ids = batches[0]
+            .column_by_name("process_id")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .unwrap();
+        assert_eq!(process_ids.value(0), "worker-central");
+    }
+
+    #[test]
+    fn collector_preserves_log_fields_sent_over_the_wire() {
+        let collector = Collector::bind("127.0.0.1:0").unwrap();
+        let addr = collector.local_addr().unwrap();
+        let store = Arc::new(Mutex::new(CollectedBatches::default()));
+        let serving_store = store.clone();
+        let _server = thread::spawn(move || collector.serve(serving_store));
+
+        let mut client = TcpStream::connect(addr).unwrap();
+        write_frame(&mut client, FRAME_HANDSHAKE, b"worker-1").unwrap();
+        let line = log_json_line("worker-1", 100, r#"{"retries":3}"#);
+        write_frame(&mut client, FRAME_LOG, line.as_bytes()).unwrap();
+        drop(client);
+
+        for _ in 0..200 {
+            if store.lock().unwrap().logs().iter().map(|b| b.num_rows()).sum::<usize>() > 0 {
+                break;
+            }
+            thread::sleep(std::time::Duration::from_millis(10));
+        }
+
+        let logs = store.lock().unwrap();
+        let batches = logs.logs();
+        assert_eq!(batches.iter().map(|b| b.num_rows()).sum::<usize>(), 1);
+
+        let fields_col = batches[0]
+            .column_by_name("fields")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .unwrap();
+        assert_eq!(fields_col.value(0), r#"{"retries":3}"#);
+    }
+}